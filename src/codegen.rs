@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::Path;
+
+use crate::compiler::{self, Instruction};
+use crate::{Result, TemplateError};
+
+/// Build-time compilation of `.tmrs` templates into plain Rust functions, in
+/// the spirit of ructe/sailfish. Call [`compile_templates`] from `build.rs`
+/// and `include!` the generated file:
+///
+/// ```no_run
+/// // build.rs
+/// let out_dir = std::env::var("OUT_DIR").unwrap();
+/// template_rs::codegen::compile_templates("templates", format!("{out_dir}/templates.rs"))
+///     .expect("failed to compile templates");
+/// ```
+///
+/// Only flat `@[name]@` substitution is supported: a template using
+/// `@if[...]@`, `@for[...]@`, or `@include[...]@` fails to compile, since
+/// those require a runtime [`crate::Template`] rather than a generated
+/// function.
+///
+/// For every `name.tmrs` file under `in_dir`, emits a function
+/// `pub fn render_name(placeholder: &str, ...) -> String`, taking one `&str`
+/// parameter per placeholder (in the order each first appears) and writing
+/// straight into a pre-sized `String` - no regex scanning or instruction
+/// walking at runtime.
+///
+/// Parameters are inferred purely from `@[...]@` usage - there's no separate
+/// manifest of "declared" placeholders to check usage against, so every
+/// placeholder a template references becomes a parameter. What compilation
+/// does reject is two differently-named placeholders sanitizing to the same
+/// Rust identifier (e.g. `@[user.name]@` and `@[user_name]@`), which would
+/// otherwise emit a function with a duplicate parameter.
+pub fn compile_templates<P: AsRef<Path>, O: AsRef<Path>>(in_dir: P, out_file: O) -> Result<()> {
+    let root = in_dir.as_ref();
+    let mut sources = Vec::new();
+    for entry in walkdir::WalkDir::new(root) {
+        let entry = entry.map_err(|e| TemplateError::Io(std::io::Error::other(e)))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if entry.path().extension().and_then(|e| e.to_str()) != Some("tmrs") {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let name = relative.with_extension("").to_string_lossy().replace('\\', "/");
+        sources.push((name, entry.path().to_path_buf()));
+    }
+    sources.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut generated = String::from("// @generated by template_rs::codegen - do not edit by hand.\n\n");
+    for (name, path) in sources {
+        let content = fs::read_to_string(&path)?;
+        generated.push_str(&render_function_for(&name, &content)?);
+        generated.push('\n');
+    }
+
+    fs::write(out_file, generated)?;
+    Ok(())
+}
+
+/// Compile one template's source into the Rust source of its generated
+/// render function.
+fn render_function_for(name: &str, content: &str) -> Result<String> {
+    let instructions = compiler::compile(content)?;
+
+    let mut params = Vec::new();
+    let mut param_idents: Vec<String> = Vec::new();
+    for instruction in &instructions {
+        match instruction {
+            Instruction::Literal(_) => {}
+            Instruction::Value { path, .. } => {
+                if params.contains(path) {
+                    continue;
+                }
+                let ident = sanitize_identifier(path);
+                if let Some(pos) = param_idents.iter().position(|i| i == &ident) {
+                    return Err(TemplateError::InvalidSyntax(format!(
+                        "template '{}' has placeholders '{}' and '{}' that both sanitize to the parameter name '{}'",
+                        name, params[pos], path, ident
+                    )));
+                }
+                param_idents.push(ident);
+                params.push(path.clone());
+            }
+            Instruction::Branch { .. } | Instruction::Iterate { .. } | Instruction::PopContext { .. } | Instruction::Include { .. } => {
+                return Err(TemplateError::InvalidSyntax(format!(
+                    "template '{}' uses conditionals, loops, or includes, which codegen does not support",
+                    name
+                )));
+            }
+        }
+    }
+
+    let fn_name = format!("render_{}", sanitize_identifier(name));
+    let param_list = params
+        .iter()
+        .map(|path| format!("{}: &str", sanitize_identifier(path)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut body = String::new();
+    for instruction in &instructions {
+        match instruction {
+            Instruction::Literal(range) => {
+                body.push_str(&format!("    output.push_str({:?});\n", &content[range.clone()]));
+            }
+            Instruction::Value { path, .. } => {
+                body.push_str(&format!("    output.push_str({});\n", sanitize_identifier(path)));
+            }
+            Instruction::Branch { .. } | Instruction::Iterate { .. } | Instruction::PopContext { .. } | Instruction::Include { .. } => {
+                unreachable!("rejected above")
+            }
+        }
+    }
+
+    Ok(format!(
+        "pub fn {fn_name}({param_list}) -> String {{\n    let mut output = String::with_capacity({capacity});\n{body}    output\n}}\n",
+        fn_name = fn_name,
+        param_list = param_list,
+        capacity = content.len(),
+        body = body,
+    ))
+}
+
+/// Turn a template name or placeholder path into a valid Rust identifier:
+/// non-alphanumeric characters become `_`, and a leading digit is prefixed
+/// with `_` since Rust identifiers can't start with one.
+fn sanitize_identifier(raw: &str) -> String {
+    let mut ident: String = raw
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::{Result, TemplateError, Value};
+
+/// A single step of a compiled template, in the style of tinytemplate's
+/// compiler/instruction split: the source is scanned once into this flat
+/// list, and rendering just walks it with a program counter instead of
+/// re-parsing on every call.
+#[derive(Debug, Clone)]
+pub(crate) enum Instruction {
+    /// A run of literal source text, referenced by byte range to avoid
+    /// copying it out at compile time.
+    Literal(Range<usize>),
+    /// `@[path]@` - substitute the resolved value, passed through the
+    /// configured escape function. `@{path}@` sets `raw` to bypass it.
+    Value { path: String, raw: bool },
+    /// `@if[path]@ ... @endif@` (or `@if[!path]@` when `invert` is set).
+    /// Jumps to `target` when the condition is not met.
+    Branch {
+        value_path: String,
+        invert: bool,
+        target: usize,
+    },
+    /// `@for[item in path]@ ... @endfor@`. Pushes a context frame binding
+    /// `item_name` to each element of `array_path` in turn.
+    Iterate {
+        array_path: String,
+        item_name: String,
+        body_start: usize,
+        body_end: usize,
+    },
+    /// Marks the end of a loop body; pops the per-iteration context frame
+    /// and either starts the next iteration or falls through once exhausted.
+    PopContext { loop_start: usize },
+    /// `@include[name]@`, or `@include[name with key=value, ...]@`. Resolved
+    /// through an [`IncludeResolver`] (normally a `TemplateRegistry`).
+    Include {
+        name: String,
+        overrides: Vec<IncludeOverride>,
+    },
+}
+
+/// One `key=value` override attached to an `@include[...]@` directive. The
+/// value is itself compiled, since it may reference the including template's
+/// context (e.g. `index=@[i]@`).
+#[derive(Debug, Clone)]
+pub(crate) struct IncludeOverride {
+    key: String,
+    source: String,
+    instructions: Vec<Instruction>,
+}
+
+enum OpenBlock {
+    If(usize),
+    For(usize),
+}
+
+/// Resolves `@include[...]@` directives during rendering. Implemented by
+/// `TemplateRegistry`; a bare [`crate::Template`] rendered without a registry
+/// uses [`NoIncludes`], which rejects any include it encounters.
+pub(crate) trait IncludeResolver {
+    fn resolve_include(
+        &self,
+        name: &str,
+        context: &HashMap<String, Value>,
+        include_stack: &mut Vec<String>,
+    ) -> Result<String>;
+}
+
+pub(crate) struct NoIncludes;
+
+impl IncludeResolver for NoIncludes {
+    fn resolve_include(&self, name: &str, _context: &HashMap<String, Value>, _include_stack: &mut Vec<String>) -> Result<String> {
+        Err(TemplateError::InvalidSyntax(format!(
+            "@include[{}]@ requires rendering through a TemplateRegistry",
+            name
+        )))
+    }
+}
+
+/// Find the `]` that matches the `[` at `open_bracket`, allowing `[`/`]`
+/// pairs to nest inside (so a nested `@[path]@` value doesn't terminate the
+/// enclosing `@include[...]@` early).
+fn find_matching_bracket(source: &str, open_bracket: usize) -> Result<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut i = open_bracket;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Err(TemplateError::InvalidSyntax("unterminated '[' in directive".into()))
+}
+
+/// Find the `}` that matches the `{` at `open_brace`, allowing nesting in
+/// the same style as [`find_matching_bracket`].
+fn find_matching_brace(source: &str, open_brace: usize) -> Result<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut i = open_brace;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Err(TemplateError::InvalidSyntax("unterminated '{' in directive".into()))
+}
+
+fn expect_at_sign(source: &str, at: usize, directive: &str) -> Result<()> {
+    if source.as_bytes().get(at) == Some(&b'@') {
+        Ok(())
+    } else {
+        Err(TemplateError::InvalidSyntax(format!("expected '@' to close {}", directive)))
+    }
+}
+
+/// Parse `name` or `name with key=value, key2=value2` into the include's
+/// target name and its compiled overrides.
+fn parse_include_spec(spec: &str) -> Result<(String, Vec<IncludeOverride>)> {
+    let (name, overrides_src) = match spec.split_once(" with ") {
+        Some((name, overrides_src)) => (name.trim().to_string(), overrides_src),
+        None => (spec.trim().to_string(), ""),
+    };
+
+    let mut overrides = Vec::new();
+    for pair in overrides_src.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| TemplateError::InvalidSyntax(format!("invalid @include[...]@ override: {}", pair)))?;
+        let source = value.trim().to_string();
+        let instructions = compile(&source)?;
+        overrides.push(IncludeOverride {
+            key: key.trim().to_string(),
+            source,
+            instructions,
+        });
+    }
+
+    Ok((name, overrides))
+}
+
+/// Compile template source into a flat instruction list. An `@` that doesn't
+/// start a recognized directive (`@if[`, `@for[`, `@include[`, `@[`, `@{`, or
+/// a matching `@endif@`/`@endfor@`) is passed through as literal text, so
+/// things like email addresses, `match x { y @ 1..=5 }`, or CSS `@media`
+/// render unchanged.
+pub(crate) fn compile(source: &str) -> Result<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+    let mut open_blocks: Vec<OpenBlock> = Vec::new();
+    let mut cursor = 0;
+    let bytes = source.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'@' {
+            i += 1;
+            continue;
+        }
+
+        let tail = &source[i + 1..];
+
+        if tail.starts_with("endif") && tail.as_bytes().get("endif".len()) == Some(&b'@') {
+            if i > cursor {
+                instructions.push(Instruction::Literal(cursor..i));
+            }
+            match open_blocks.pop() {
+                Some(OpenBlock::If(idx)) => {
+                    let target = instructions.len();
+                    if let Instruction::Branch { target: t, .. } = &mut instructions[idx] {
+                        *t = target;
+                    }
+                }
+                _ => return Err(TemplateError::InvalidSyntax("@endif@ without matching @if[...]@".into())),
+            }
+            i += 1 + "endif".len() + 1;
+            cursor = i;
+            continue;
+        }
+
+        if tail.starts_with("endfor") && tail.as_bytes().get("endfor".len()) == Some(&b'@') {
+            if i > cursor {
+                instructions.push(Instruction::Literal(cursor..i));
+            }
+            match open_blocks.pop() {
+                Some(OpenBlock::For(idx)) => {
+                    let pop_idx = instructions.len();
+                    instructions.push(Instruction::PopContext { loop_start: idx });
+                    if let Instruction::Iterate { body_end, .. } = &mut instructions[idx] {
+                        *body_end = pop_idx;
+                    }
+                }
+                _ => return Err(TemplateError::InvalidSyntax("@endfor@ without matching @for[...]@".into())),
+            }
+            i += 1 + "endfor".len() + 1;
+            cursor = i;
+            continue;
+        }
+
+        if tail.starts_with("if[") {
+            let open_bracket = i + 1 + "if".len();
+            let close = find_matching_bracket(source, open_bracket)?;
+            expect_at_sign(source, close + 1, "@if[...]@")?;
+            if i > cursor {
+                instructions.push(Instruction::Literal(cursor..i));
+            }
+            let cond = source[open_bracket + 1..close].trim();
+            let (invert, value_path) = match cond.strip_prefix('!') {
+                Some(rest) => (true, rest.trim().to_string()),
+                None => (false, cond.to_string()),
+            };
+            open_blocks.push(OpenBlock::If(instructions.len()));
+            instructions.push(Instruction::Branch {
+                value_path,
+                invert,
+                target: 0,
+            });
+            i = close + 2;
+            cursor = i;
+            continue;
+        }
+
+        if tail.starts_with("for[") {
+            let open_bracket = i + 1 + "for".len();
+            let close = find_matching_bracket(source, open_bracket)?;
+            expect_at_sign(source, close + 1, "@for[...]@")?;
+            if i > cursor {
+                instructions.push(Instruction::Literal(cursor..i));
+            }
+            let loop_expr = source[open_bracket + 1..close].trim();
+            let (item_name, array_path) = loop_expr
+                .split_once(" in ")
+                .map(|(item, array)| (item.trim().to_string(), array.trim().to_string()))
+                .ok_or_else(|| TemplateError::InvalidSyntax(format!("invalid @for[...]@ directive: {}", loop_expr)))?;
+            open_blocks.push(OpenBlock::For(instructions.len()));
+            instructions.push(Instruction::Iterate {
+                array_path,
+                item_name,
+                body_start: instructions.len() + 1,
+                body_end: 0,
+            });
+            i = close + 2;
+            cursor = i;
+            continue;
+        }
+
+        if tail.starts_with("include[") {
+            let open_bracket = i + 1 + "include".len();
+            let close = find_matching_bracket(source, open_bracket)?;
+            expect_at_sign(source, close + 1, "@include[...]@")?;
+            if i > cursor {
+                instructions.push(Instruction::Literal(cursor..i));
+            }
+            let spec = source[open_bracket + 1..close].trim();
+            let (name, overrides) = parse_include_spec(spec)?;
+            instructions.push(Instruction::Include { name, overrides });
+            i = close + 2;
+            cursor = i;
+            continue;
+        }
+
+        if tail.starts_with('[') {
+            let open_bracket = i + 1;
+            let close = find_matching_bracket(source, open_bracket)?;
+            expect_at_sign(source, close + 1, "@[...]@")?;
+            if i > cursor {
+                instructions.push(Instruction::Literal(cursor..i));
+            }
+            let path = source[open_bracket + 1..close].trim().to_string();
+            instructions.push(Instruction::Value { path, raw: false });
+            i = close + 2;
+            cursor = i;
+            continue;
+        }
+
+        if tail.starts_with('{') {
+            let open_brace = i + 1;
+            let close = find_matching_brace(source, open_brace)?;
+            expect_at_sign(source, close + 1, "@{...}@")?;
+            if i > cursor {
+                instructions.push(Instruction::Literal(cursor..i));
+            }
+            let path = source[open_brace + 1..close].trim().to_string();
+            instructions.push(Instruction::Value { path, raw: true });
+            i = close + 2;
+            cursor = i;
+            continue;
+        }
+
+        // Not a recognized directive - treat the `@` as literal text (an
+        // email address, a `match ... @` binding, CSS `@media`, etc.) rather
+        // than rejecting the whole template, matching the baseline behavior
+        // of leaving stray `@` untouched.
+        i += 1;
+    }
+
+    if cursor < source.len() {
+        instructions.push(Instruction::Literal(cursor..source.len()));
+    }
+
+    if !open_blocks.is_empty() {
+        return Err(TemplateError::InvalidSyntax(
+            "unclosed @if[...]@ or @for[...]@ block".into(),
+        ));
+    }
+
+    Ok(instructions)
+}
+
+/// Resolve a dotted path against a stack of context frames, innermost first.
+fn resolve(frames: &[Value], path: &str) -> Option<Value> {
+    let mut parts = path.split('.');
+    let first = parts.next()?;
+    let mut current = frames.iter().rev().find_map(|frame| match frame {
+        Value::Map(m) => m.get(first).cloned(),
+        _ => None,
+    })?;
+    for part in parts {
+        current = match current {
+            Value::Map(m) => m.get(part).cloned()?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Flatten a context stack into a single map, with inner frames taking
+/// precedence over outer ones - used to build the inherited context passed
+/// to `@include[...]@`.
+fn flatten(frames: &[Value]) -> HashMap<String, Value> {
+    let mut merged = HashMap::new();
+    for frame in frames {
+        if let Value::Map(m) = frame {
+            for (k, v) in m {
+                merged.insert(k.clone(), v.clone());
+            }
+        }
+    }
+    merged
+}
+
+struct LoopState {
+    items: Vec<Value>,
+    index: usize,
+    item_name: String,
+}
+
+/// Walk `instructions` with a program counter and a stack of context frames,
+/// writing rendered output into `output`. `resolver` and `include_stack`
+/// handle `@include[...]@`; pass [`NoIncludes`] and an empty stack when
+/// rendering a bare template with no registry. Every substituted `@[...]@`
+/// value is passed through `escape`, unless the directive used the raw
+/// `@{...}@` form.
+pub(crate) fn execute(
+    instructions: &[Instruction],
+    source: &str,
+    root: Value,
+    output: &mut String,
+    resolver: &dyn IncludeResolver,
+    include_stack: &mut Vec<String>,
+    escape: &dyn Fn(&str) -> String,
+) -> Result<()> {
+    let mut frames: Vec<Value> = vec![root];
+    let mut loops: Vec<LoopState> = Vec::new();
+    let mut pc = 0;
+
+    while pc < instructions.len() {
+        match &instructions[pc] {
+            Instruction::Literal(range) => {
+                output.push_str(&source[range.clone()]);
+                pc += 1;
+            }
+            Instruction::Value { path, raw } => {
+                let resolved = resolve(&frames, path).ok_or_else(|| TemplateError::MissingPlaceholder(path.clone()))?;
+                let value = resolved
+                    .as_str()
+                    .ok_or_else(|| TemplateError::MissingPlaceholder(path.clone()))?;
+                if *raw {
+                    output.push_str(value);
+                } else {
+                    output.push_str(&escape(value));
+                }
+                pc += 1;
+            }
+            Instruction::Branch {
+                value_path,
+                invert,
+                target,
+            } => {
+                let truthy = resolve(&frames, value_path).map(|v| v.is_truthy()).unwrap_or(false);
+                let enter = if *invert { !truthy } else { truthy };
+                pc = if enter { pc + 1 } else { *target };
+            }
+            Instruction::Iterate {
+                array_path,
+                item_name,
+                body_start,
+                body_end,
+            } => {
+                let items = match resolve(&frames, array_path) {
+                    Some(Value::Array(items)) => items,
+                    _ => Vec::new(),
+                };
+                if items.is_empty() {
+                    pc = body_end + 1;
+                } else {
+                    let first = items[0].clone();
+                    loops.push(LoopState {
+                        items,
+                        index: 0,
+                        item_name: item_name.clone(),
+                    });
+                    let mut child = HashMap::new();
+                    child.insert(item_name.clone(), first);
+                    frames.push(Value::Map(child));
+                    pc = *body_start;
+                }
+            }
+            Instruction::PopContext { loop_start } => {
+                frames.pop();
+                let state = loops.last_mut().expect("PopContext without an active loop");
+                state.index += 1;
+                if state.index < state.items.len() {
+                    let next = state.items[state.index].clone();
+                    let mut child = HashMap::new();
+                    child.insert(state.item_name.clone(), next);
+                    frames.push(Value::Map(child));
+                    pc = loop_start + 1;
+                } else {
+                    loops.pop();
+                    pc += 1;
+                }
+            }
+            Instruction::Include { name, overrides } => {
+                let mut context = flatten(&frames);
+                for ov in overrides {
+                    // Override values are data handed to the partial, not
+                    // output themselves - the partial's own `@[...]@`
+                    // substitution applies `escape` when it consumes them,
+                    // so escaping here too would double-escape.
+                    let mut value_out = String::new();
+                    execute(
+                        &ov.instructions,
+                        &ov.source,
+                        Value::Map(context.clone()),
+                        &mut value_out,
+                        resolver,
+                        include_stack,
+                        &crate::escape::no_escape,
+                    )?;
+                    context.insert(ov.key.clone(), Value::String(value_out));
+                }
+                let rendered = resolver.resolve_include(name, &context, include_stack)?;
+                output.push_str(&rendered);
+                pc += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
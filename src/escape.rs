@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+/// A value transform applied to every substituted `@[...]@` placeholder,
+/// settable on a [`crate::Template`] or [`crate::TemplateRegistry`].
+///
+/// `@{name}@` bypasses whatever `EscapeFn` is configured, for values already
+/// known to be safe.
+pub type EscapeFn = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Escapes `&`, `"`, `<`, and `>` into their HTML/XML entities.
+pub fn html_escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The identity transform: substitutes values unchanged. This is the
+/// default, since the crate's primary use case is generating Rust source,
+/// where HTML-style escaping would corrupt the output.
+pub fn no_escape(input: &str) -> String {
+    input.to_string()
+}
@@ -1,8 +1,19 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
 use thiserror::Error;
 
+pub mod codegen;
+mod compiler;
+mod escape;
+mod value;
+
+use compiler::Instruction;
+pub use escape::{html_escape, no_escape, EscapeFn};
+pub use value::Value;
+
 #[derive(Error, Debug)]
 pub enum TemplateError {
     #[error("IO error: {0}")]
@@ -87,21 +98,68 @@ impl TemplateRef {
     }
 }
 
+/// The parts of a [`Template`] that are re-derived from source text, kept
+/// together so `dev_mode` can swap them all out atomically on reload.
 #[derive(Debug, Clone)]
-pub struct Template {
+struct Compiled {
     content: String,
     placeholders: HashMap<String, String>,
+    instructions: Vec<Instruction>,
+    modified: Option<SystemTime>,
+}
+
+impl Compiled {
+    fn from_content(content: &str) -> Result<Self> {
+        let instructions = compiler::compile(content)?;
+        Ok(Self {
+            placeholders: Template::extract_placeholders(&instructions),
+            instructions,
+            content: content.to_string(),
+            modified: None,
+        })
+    }
+}
+
+/// `compiled` is a [`Mutex`] rather than a [`std::cell::RefCell`] so `Template`
+/// stays `Sync` despite `dev_mode`'s interior mutability - needed so a
+/// [`TemplateRegistry`] can be shared across threads for the lifetime of a
+/// program, per [`TemplateRegistry`]'s own doc comment.
+pub struct Template {
+    compiled: Mutex<Compiled>,
     path: Option<PathBuf>,
+    dev_mode: bool,
+    escape_fn: EscapeFn,
+}
+
+impl Clone for Template {
+    fn clone(&self) -> Self {
+        Self {
+            compiled: Mutex::new(self.compiled.lock().expect("compiled mutex poisoned").clone()),
+            path: self.path.clone(),
+            dev_mode: self.dev_mode,
+            escape_fn: self.escape_fn.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Template")
+            .field("compiled", &self.compiled)
+            .field("path", &self.path)
+            .field("dev_mode", &self.dev_mode)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Template {
     /// Create a new template from a string
     pub fn new(content: &str) -> Result<Self> {
-        let placeholders = Self::extract_placeholders(content)?;
         Ok(Self {
-            content: content.to_string(),
-            placeholders,
+            compiled: Mutex::new(Compiled::from_content(content)?),
             path: None,
+            dev_mode: false,
+            escape_fn: std::sync::Arc::new(no_escape),
         })
     }
 
@@ -110,45 +168,161 @@ impl Template {
         let content = fs::read_to_string(&path)?;
         let mut template = Self::new(&content)?;
         template.path = Some(path.as_ref().to_path_buf());
+        template.compiled.get_mut().expect("compiled mutex poisoned").modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
         Ok(template)
     }
 
+    /// Enable or disable `dev_mode`. When enabled and the template was loaded
+    /// from a file, every `render*` call checks the file's modification time
+    /// and reloads the source if it changed, mirroring handlebars' `dev_mode`.
+    /// Templates not loaded via [`Template::from_file`] are unaffected.
+    ///
+    /// Values previously passed to [`Template::set`] are carried forward
+    /// across a reload for any placeholder that still exists in the new
+    /// source; placeholders removed from the source are dropped, and ones
+    /// newly added start out unset.
+    pub fn set_dev_mode(&mut self, enabled: bool) {
+        self.dev_mode = enabled;
+    }
+
+    /// Set the function applied to every substituted `@[...]@` value, e.g.
+    /// [`html_escape`]. Defaults to [`no_escape`]. Use `@{name}@` in the
+    /// template source to opt a single placeholder out of whatever is
+    /// configured here.
+    pub fn set_escape_fn(&mut self, escape_fn: EscapeFn) {
+        self.escape_fn = escape_fn;
+    }
+
+    /// Re-read the backing file and recompile if `dev_mode` is enabled and
+    /// the file's modification time has moved on. A no-op otherwise, so the
+    /// default (non-dev-mode) path never touches the filesystem on render.
+    fn reload_if_needed(&self) -> Result<()> {
+        if !self.dev_mode {
+            return Ok(());
+        }
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut compiled = self.compiled.lock().expect("compiled mutex poisoned");
+        let stale = modified.is_some() && modified != compiled.modified;
+        if stale {
+            let content = fs::read_to_string(path)?;
+            let mut fresh = Compiled::from_content(&content)?;
+            fresh.modified = modified;
+            for (placeholder, value) in fresh.placeholders.iter_mut() {
+                if let Some(carried) = compiled.placeholders.get(placeholder) {
+                    *value = carried.clone();
+                }
+            }
+            *compiled = fresh;
+        }
+        Ok(())
+    }
+
     /// Extract placeholders from template content
-    fn extract_placeholders(content: &str) -> Result<HashMap<String, String>> {
+    /// Collect the set of `@[path]@`/`@{path}@` placeholders an already-compiled
+    /// template references, for [`Template::set`] to validate against. Reads the
+    /// instruction list rather than re-scanning the source text with a regex, so
+    /// directives that sit right next to a substitution (e.g.
+    /// `@for[item in items]@[@[item]@]@endfor@`) can't be misparsed into a bogus
+    /// placeholder name.
+    fn extract_placeholders(instructions: &[Instruction]) -> HashMap<String, String> {
         let mut placeholders = HashMap::new();
-        // Pattern for @[placeholder_name]@ - using @ symbols which are invalid in Rust
-        let pattern = regex::Regex::new(r"@\[([^]]+)\]@").unwrap();
-        
-        for capture in pattern.captures_iter(content) {
-            let placeholder = capture.get(1).unwrap().as_str().trim();
-            placeholders.insert(placeholder.to_string(), String::new());
+        for instruction in instructions {
+            if let Instruction::Value { path, .. } = instruction {
+                placeholders.entry(path.clone()).or_insert_with(String::new);
+            }
         }
-        
-        Ok(placeholders)
+        placeholders
     }
 
     /// Set a value for a placeholder
     pub fn set(&mut self, placeholder: &str, value: &str) -> Result<()> {
-        if !self.placeholders.contains_key(placeholder) {
+        let compiled = self.compiled.get_mut().expect("compiled mutex poisoned");
+        if !compiled.placeholders.contains_key(placeholder) {
             return Err(TemplateError::MissingPlaceholder(placeholder.to_string()));
         }
-        self.placeholders.insert(placeholder.to_string(), value.to_string());
+        compiled.placeholders.insert(placeholder.to_string(), value.to_string());
         Ok(())
     }
 
     /// Render the template with current placeholder values
     pub fn render(&self) -> Result<String> {
-        let mut result = self.content.clone();
-        
-        for (placeholder, value) in &self.placeholders {
-            let pattern = format!("@[{}]@", placeholder);
-            if value.is_empty() {
-                return Err(TemplateError::MissingPlaceholder(placeholder.clone()));
+        self.render_with(&HashMap::new())
+    }
+
+    /// Render the template, overriding placeholder values for this call only.
+    ///
+    /// Values passed in `overrides` take precedence over any value set with
+    /// [`Template::set`]; the template's own placeholder map is left untouched.
+    pub fn render_with(&self, overrides: &HashMap<String, String>) -> Result<String> {
+        self.reload_if_needed()?;
+        let overrides: HashMap<String, Value> = overrides.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect();
+        let context = self.merge_placeholders(&overrides);
+        self.render_context(&context)
+    }
+
+    /// Build a full context by layering `overrides` over this template's own
+    /// placeholder values (skipping placeholders that are still unset).
+    pub(crate) fn merge_placeholders(&self, overrides: &HashMap<String, Value>) -> HashMap<String, Value> {
+        let mut context = HashMap::new();
+        {
+            let compiled = self.compiled.lock().expect("compiled mutex poisoned");
+            for (placeholder, value) in &compiled.placeholders {
+                if !value.is_empty() {
+                    context.insert(placeholder.clone(), Value::String(value.clone()));
+                }
             }
-            result = result.replace(&pattern, value);
         }
-        
-        Ok(result)
+        for (key, value) in overrides {
+            context.insert(key.clone(), value.clone());
+        }
+        context
+    }
+
+    /// Render the template against a full [`Value`] context, supporting
+    /// dotted paths, `@if[...]@` branches and `@for[...]@` loops.
+    ///
+    /// `@include[...]@` directives are rejected, since a bare template has no
+    /// registry to resolve them against; render through a [`TemplateRegistry`]
+    /// instead when the template uses includes.
+    pub fn render_context(&self, context: &HashMap<String, Value>) -> Result<String> {
+        self.render_via(context, &compiler::NoIncludes, &mut Vec::new())
+    }
+
+    /// Like [`Self::render_context`], but resolving `@include[...]@`
+    /// directives through `resolver`, tracking `include_stack` to detect
+    /// cycles.
+    pub(crate) fn render_via(
+        &self,
+        context: &HashMap<String, Value>,
+        resolver: &dyn compiler::IncludeResolver,
+        include_stack: &mut Vec<String>,
+    ) -> Result<String> {
+        self.reload_if_needed()?;
+        // Clone the instructions/content out and drop the lock before
+        // executing: `@include[...]@` can recurse back into this same
+        // `Template` (e.g. a self-referencing include cycle) before the
+        // cycle check fires, and `Mutex`, unlike the `RefCell` this used to
+        // be, deadlocks rather than panics on a reentrant lock from the same
+        // thread.
+        let (instructions, content) = {
+            let compiled = self.compiled.lock().expect("compiled mutex poisoned");
+            (compiled.instructions.clone(), compiled.content.clone())
+        };
+        let mut output = String::new();
+        compiler::execute(
+            &instructions,
+            &content,
+            Value::Map(context.clone()),
+            &mut output,
+            resolver,
+            include_stack,
+            self.escape_fn.as_ref(),
+        )?;
+        Ok(output)
     }
 }
 
@@ -157,6 +331,12 @@ pub struct TemplateAssembler {
     templates: Vec<Template>,
 }
 
+impl Default for TemplateAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TemplateAssembler {
     pub fn new() -> Self {
         Self {
@@ -172,7 +352,7 @@ impl TemplateAssembler {
     /// Set a value for a placeholder across all templates
     pub fn set_global(&mut self, placeholder: &str, value: &str) -> Result<()> {
         for template in &mut self.templates {
-            if template.placeholders.contains_key(placeholder) {
+            if template.compiled.get_mut().expect("compiled mutex poisoned").placeholders.contains_key(placeholder) {
                 template.set(placeholder, value)?;
             }
         }
@@ -190,6 +370,164 @@ impl TemplateAssembler {
     }
 }
 
+/// A named collection of templates that can reference and render one another.
+///
+/// Unlike a bare [`Template`], a registry is meant to be built once and reused
+/// for the lifetime of a program: templates are registered under a name, and
+/// `render` supplies placeholder values at call time instead of mutating the
+/// stored template. A set of default values can be registered once and will
+/// resolve across every template rendered through the registry.
+pub struct TemplateRegistry {
+    templates: HashMap<String, Template>,
+    defaults: HashMap<String, String>,
+    dev_mode: bool,
+    escape_fn: EscapeFn,
+}
+
+impl std::fmt::Debug for TemplateRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TemplateRegistry")
+            .field("templates", &self.templates)
+            .field("defaults", &self.defaults)
+            .field("dev_mode", &self.dev_mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for TemplateRegistry {
+    fn default() -> Self {
+        Self {
+            templates: HashMap::new(),
+            defaults: HashMap::new(),
+            dev_mode: false,
+            escape_fn: std::sync::Arc::new(no_escape),
+        }
+    }
+}
+
+impl TemplateRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a template under `name`, parsed from an in-memory string.
+    pub fn register_template(&mut self, name: &str, content: &str) -> Result<()> {
+        let mut template = Template::new(content)?;
+        template.set_dev_mode(self.dev_mode);
+        template.set_escape_fn(self.escape_fn.clone());
+        self.templates.insert(name.to_string(), template);
+        Ok(())
+    }
+
+    /// Register a template under `name`, loaded from a file on disk.
+    pub fn register_template_file<P: AsRef<Path>>(&mut self, name: &str, path: P) -> Result<()> {
+        let mut template = Template::from_file(path)?;
+        template.set_dev_mode(self.dev_mode);
+        template.set_escape_fn(self.escape_fn.clone());
+        self.templates.insert(name.to_string(), template);
+        Ok(())
+    }
+
+    /// Walk `dir` and register every file whose extension matches
+    /// `extension` (`.tmrs` when `None`), naming each template after its path
+    /// relative to `dir` with the extension stripped and separators
+    /// normalized to `/` - so `components/button.tmrs` becomes
+    /// `components/button` even when walked on Windows.
+    pub fn register_templates_dir<P: AsRef<Path>>(&mut self, dir: P, extension: Option<&str>) -> Result<()> {
+        let extension = extension.unwrap_or("tmrs").trim_start_matches('.');
+        let root = dir.as_ref();
+
+        for entry in walkdir::WalkDir::new(root) {
+            let entry = entry.map_err(|e| TemplateError::Io(std::io::Error::other(e)))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            if entry.path().extension().and_then(|e| e.to_str()) != Some(extension) {
+                continue;
+            }
+
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            let name = relative.with_extension("").to_string_lossy().replace('\\', "/");
+
+            self.register_template_file(&name, entry.path())?;
+        }
+
+        Ok(())
+    }
+
+    /// Set a default value applied to every template rendered through this
+    /// registry, unless overridden by the `data` passed to [`Self::render`].
+    pub fn set_default(&mut self, placeholder: &str, value: &str) {
+        self.defaults.insert(placeholder.to_string(), value.to_string());
+    }
+
+    /// Enable or disable `dev_mode` for this registry and every template
+    /// already registered in it; templates registered afterwards inherit the
+    /// current setting automatically.
+    pub fn set_dev_mode(&mut self, enabled: bool) {
+        self.dev_mode = enabled;
+        for template in self.templates.values_mut() {
+            template.set_dev_mode(enabled);
+        }
+    }
+
+    /// Set the escape function applied across every template in this
+    /// registry, including ones already registered; templates registered
+    /// afterwards inherit the current setting automatically. See
+    /// [`Template::set_escape_fn`].
+    pub fn set_escape_fn(&mut self, escape_fn: EscapeFn) {
+        self.escape_fn = escape_fn.clone();
+        for template in self.templates.values_mut() {
+            template.set_escape_fn(escape_fn.clone());
+        }
+    }
+
+    /// Render the template registered under `name`, applying `data` over the
+    /// registry's default values. `@include[...]@` directives in the
+    /// template resolve to other templates in this registry.
+    pub fn render(&self, name: &str, data: &HashMap<String, String>) -> Result<String> {
+        let mut values = self.defaults.clone();
+        values.extend(data.iter().map(|(k, v)| (k.clone(), v.clone())));
+        let context: HashMap<String, Value> = values.into_iter().map(|(k, v)| (k, Value::String(v))).collect();
+        self.render_named(name, &context, &mut Vec::new())
+    }
+
+    /// Like [`Self::render`], but accepting a full [`Value`] context so
+    /// `@for[...]@` and `@if[...]@` can be driven through the registry too.
+    pub fn render_context(&self, name: &str, context: &HashMap<String, Value>) -> Result<String> {
+        let mut values: HashMap<String, Value> = self.defaults.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect();
+        values.extend(context.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.render_named(name, &values, &mut Vec::new())
+    }
+
+    fn render_named(&self, name: &str, context: &HashMap<String, Value>, include_stack: &mut Vec<String>) -> Result<String> {
+        let template = self
+            .templates
+            .get(name)
+            .ok_or_else(|| TemplateError::Parse(format!("template not found: {}", name)))?;
+        let merged = template.merge_placeholders(context);
+        template.render_via(&merged, self, include_stack)
+    }
+}
+
+impl compiler::IncludeResolver for TemplateRegistry {
+    fn resolve_include(&self, name: &str, context: &HashMap<String, Value>, include_stack: &mut Vec<String>) -> Result<String> {
+        if include_stack.iter().any(|included| included == name) {
+            let mut cycle = include_stack.clone();
+            cycle.push(name.to_string());
+            return Err(TemplateError::InvalidSyntax(format!(
+                "include cycle detected: {}",
+                cycle.join(" -> ")
+            )));
+        }
+        include_stack.push(name.to_string());
+        let result = self.render_named(name, context, include_stack);
+        include_stack.pop();
+        result
+    }
+}
+
 #[cfg(all(test, feature = "execute"))]
 mod tests {
     use super::*;
@@ -235,4 +573,391 @@ mod tests {
             Err(TemplateError::MissingPlaceholder(_))
         ));
     }
+}
+
+#[cfg(test)]
+mod registry_tests {
+    use super::*;
+
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn test_template_and_registry_are_sync() {
+        // A TemplateRegistry is meant to be a long-lived engine shared across
+        // a program, so both it and the Template it stores must stay `Sync`
+        // despite dev_mode's interior mutability.
+        assert_sync::<Template>();
+        assert_sync::<TemplateRegistry>();
+    }
+
+    #[test]
+    fn test_register_and_render() -> Result<()> {
+        let mut registry = TemplateRegistry::new();
+        registry.register_template("greeting", "Hello, @[name]@!")?;
+
+        let mut data = HashMap::new();
+        data.insert("name".to_string(), "World".to_string());
+
+        assert_eq!(registry.render("greeting", &data)?, "Hello, World!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_defaults_resolve_across_templates() -> Result<()> {
+        let mut registry = TemplateRegistry::new();
+        registry.register_template("a", "@[project]@ says hi")?;
+        registry.register_template("b", "@[project]@ says bye")?;
+        registry.set_default("project", "template_rs");
+
+        let data = HashMap::new();
+        assert_eq!(registry.render("a", &data)?, "template_rs says hi");
+        assert_eq!(registry.render("b", &data)?, "template_rs says bye");
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_missing_template() {
+        let registry = TemplateRegistry::new();
+        let result = registry.render("missing", &HashMap::new());
+        assert!(matches!(result, Err(TemplateError::Parse(_))));
+    }
+}
+
+#[cfg(test)]
+mod dir_source_tests {
+    use super::*;
+
+    #[test]
+    fn test_register_templates_dir_names_by_relative_path() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("template_rs_dir_source_{}", std::process::id()));
+        fs::create_dir_all(root.join("components"))?;
+        fs::write(root.join("page.tmrs"), "page: @[title]@")?;
+        fs::write(root.join("components/button.tmrs"), "button: @[label]@")?;
+        fs::write(root.join("ignored.txt"), "not a template")?;
+
+        let mut registry = TemplateRegistry::new();
+        registry.register_templates_dir(&root, None)?;
+
+        let mut data = HashMap::new();
+        data.insert("title".to_string(), "Home".to_string());
+        assert_eq!(registry.render("page", &data)?, "page: Home");
+
+        let mut data = HashMap::new();
+        data.insert("label".to_string(), "Go".to_string());
+        assert_eq!(registry.render("components/button", &data)?, "button: Go");
+
+        assert!(registry.render("ignored", &HashMap::new()).is_err());
+
+        fs::remove_dir_all(&root).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod include_tests {
+    use super::*;
+
+    #[test]
+    fn test_include_splices_rendered_output() -> Result<()> {
+        let mut registry = TemplateRegistry::new();
+        registry.register_template("header", "== @[title]@ ==")?;
+        registry.register_template("page", "@include[header]@\nbody")?;
+
+        let mut data = HashMap::new();
+        data.insert("title".to_string(), "Home".to_string());
+
+        assert_eq!(registry.render("page", &data)?, "== Home ==\nbody");
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_with_local_override() -> Result<()> {
+        let mut registry = TemplateRegistry::new();
+        registry.register_template("row", "[@[index]@]")?;
+        registry.register_template("list", "@for[i in items]@@include[row with index=@[i]@]@@endfor@")?;
+
+        let mut context = HashMap::new();
+        context.insert(
+            "items".to_string(),
+            Value::Array(vec![Value::String("a".to_string()), Value::String("b".to_string())]),
+        );
+
+        assert_eq!(registry.render_context("list", &context)?, "[a][b]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_cycle_is_rejected() {
+        let mut registry = TemplateRegistry::new();
+        registry.register_template("a", "@include[b]@").unwrap();
+        registry.register_template("b", "@include[a]@").unwrap();
+
+        let result = registry.render("a", &HashMap::new());
+        assert!(matches!(result, Err(TemplateError::InvalidSyntax(_))));
+    }
+}
+
+#[cfg(test)]
+mod control_flow_tests {
+    use super::*;
+
+    #[test]
+    fn test_if_renders_body_when_truthy() -> Result<()> {
+        let template = Template::new("@if[show]@hello@endif@")?;
+        let mut context = HashMap::new();
+        context.insert("show".to_string(), Value::String("yes".to_string()));
+        assert_eq!(template.render_context(&context)?, "hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_if_skips_body_when_falsy() -> Result<()> {
+        let template = Template::new("@if[show]@hello@endif@")?;
+        let context = HashMap::new();
+        assert_eq!(template.render_context(&context)?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_inverted_if() -> Result<()> {
+        let template = Template::new("@if[!show]@hidden@endif@")?;
+        let context = HashMap::new();
+        assert_eq!(template.render_context(&context)?, "hidden");
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_loop_over_array() -> Result<()> {
+        let template = Template::new("@for[item in items]@[@[item]@]@endfor@")?;
+        let mut context = HashMap::new();
+        context.insert(
+            "items".to_string(),
+            Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+                Value::String("c".to_string()),
+            ]),
+        );
+        assert_eq!(template.render_context(&context)?, "[a][b][c]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_placeholder_extraction_ignores_adjacent_loop_syntax() -> Result<()> {
+        // A regex scan over the source misparses the `]@[` run between the
+        // loop body's literal brackets and its `@[item]@` substitution as a
+        // stray placeholder named `@[item`; deriving placeholders from the
+        // compiled instructions instead must only see the real one.
+        let template = Template::new("@for[item in items]@[@[item]@]@endfor@")?;
+        let placeholders: Vec<String> = template
+            .compiled
+            .lock()
+            .expect("compiled mutex poisoned")
+            .placeholders
+            .keys()
+            .cloned()
+            .collect();
+        assert_eq!(placeholders, vec!["item".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dotted_path_resolution() -> Result<()> {
+        let template = Template::new("@[user.name]@")?;
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), Value::String("Ada".to_string()));
+        let mut context = HashMap::new();
+        context.insert("user".to_string(), Value::Map(user));
+        assert_eq!(template.render_context(&context)?, "Ada");
+        Ok(())
+    }
+
+    #[test]
+    fn test_unclosed_if_is_invalid_syntax() {
+        let result = Template::new("@if[show]@hello");
+        assert!(matches!(result, Err(TemplateError::InvalidSyntax(_))));
+    }
+
+    #[test]
+    fn test_unrecognized_at_sign_is_treated_as_literal() -> Result<()> {
+        let template = Template::new("match x { y @ 1..=5 => {} } contact foo@bar.com @media")?;
+        assert_eq!(
+            template.render()?,
+            "match x { y @ 1..=5 => {} } contact foo@bar.com @media"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod dev_mode_tests {
+    use super::*;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    fn overrides(greeting: &str) -> HashMap<String, String> {
+        let mut overrides = HashMap::new();
+        overrides.insert("greeting".to_string(), greeting.to_string());
+        overrides
+    }
+
+    #[test]
+    fn test_dev_mode_reloads_changed_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("template_rs_dev_mode_{}.tmrs", std::process::id()));
+        fs::write(&path, "@[greeting]@, World!")?;
+
+        let mut template = Template::from_file(&path)?;
+        template.set_dev_mode(true);
+        assert_eq!(template.render_with(&overrides("Hello"))?, "Hello, World!");
+
+        sleep(Duration::from_millis(20));
+        fs::write(&path, "@[greeting]@, Rust!")?;
+        assert_eq!(template.render_with(&overrides("Hello"))?, "Hello, Rust!");
+
+        fs::remove_file(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_dev_mode_keeps_stale_content() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("template_rs_no_dev_mode_{}.tmrs", std::process::id()));
+        fs::write(&path, "@[greeting]@, World!")?;
+
+        let template = Template::from_file(&path)?;
+        assert_eq!(template.render_with(&overrides("Hello"))?, "Hello, World!");
+
+        sleep(Duration::from_millis(20));
+        fs::write(&path, "@[greeting]@, Rust!")?;
+        assert_eq!(template.render_with(&overrides("Hello"))?, "Hello, World!");
+
+        fs::remove_file(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_dev_mode_reload_carries_forward_values_set_via_set() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("template_rs_dev_mode_carry_{}.tmrs", std::process::id()));
+        fs::write(&path, "@[greeting]@, @[name]@!")?;
+
+        let mut template = Template::from_file(&path)?;
+        template.set_dev_mode(true);
+        template.set("greeting", "Hello")?;
+        template.set("name", "World")?;
+        assert_eq!(template.render()?, "Hello, World!");
+
+        sleep(Duration::from_millis(20));
+        fs::write(&path, "@[greeting]@, dear @[name]@!")?;
+        assert_eq!(template.render()?, "Hello, dear World!");
+
+        fs::remove_file(&path).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod escape_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_escape_is_identity() -> Result<()> {
+        let mut template = Template::new("@[body]@")?;
+        template.set("body", "<b>&\"hi\"</b>")?;
+        assert_eq!(template.render()?, "<b>&\"hi\"</b>");
+        Ok(())
+    }
+
+    #[test]
+    fn test_html_escape_is_applied() -> Result<()> {
+        let mut template = Template::new("@[body]@")?;
+        template.set_escape_fn(std::sync::Arc::new(html_escape));
+        template.set("body", "<b>&\"hi\"</b>")?;
+        assert_eq!(template.render()?, "&lt;b&gt;&amp;&quot;hi&quot;&lt;/b&gt;");
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_marker_bypasses_escape_fn() -> Result<()> {
+        let mut template = Template::new("@{body}@")?;
+        template.set_escape_fn(std::sync::Arc::new(html_escape));
+        template.set("body", "<b>hi</b>")?;
+        assert_eq!(template.render()?, "<b>hi</b>");
+        Ok(())
+    }
+
+    #[test]
+    fn test_registry_propagates_escape_fn_to_registered_templates() -> Result<()> {
+        let mut registry = TemplateRegistry::new();
+        registry.set_escape_fn(std::sync::Arc::new(html_escape));
+        registry.register_template("greeting", "@[name]@")?;
+
+        let mut data = HashMap::new();
+        data.insert("name".to_string(), "<script>".to_string());
+        assert_eq!(registry.render("greeting", &data)?, "&lt;script&gt;");
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_overrides_escape_exactly_once() -> Result<()> {
+        let mut registry = TemplateRegistry::new();
+        registry.set_escape_fn(std::sync::Arc::new(html_escape));
+        registry.register_template("row", "[@[index]@]")?;
+        registry.register_template("list", "@include[row with index=@[i]@]@")?;
+
+        let mut context = HashMap::new();
+        context.insert("i".to_string(), Value::String("<x>".to_string()));
+
+        assert_eq!(registry.render_context("list", &context)?, "[&lt;x&gt;]");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod codegen_tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_templates_generates_typed_render_function() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("template_rs_codegen_{}", std::process::id()));
+        fs::create_dir_all(&root)?;
+        fs::write(root.join("hello_world.tmrs"), "@[greeting]@, @[name]@!")?;
+
+        let out_file = root.join("templates.rs");
+        codegen::compile_templates(&root, &out_file)?;
+
+        let generated = fs::read_to_string(&out_file)?;
+        assert!(generated.contains("pub fn render_hello_world(greeting: &str, name: &str) -> String"));
+        assert!(generated.contains("output.push_str(greeting);"));
+        assert!(generated.contains("output.push_str(\", \");"));
+
+        fs::remove_dir_all(&root).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_compile_templates_rejects_control_flow() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("template_rs_codegen_rejects_{}", std::process::id()));
+        fs::create_dir_all(&root)?;
+        fs::write(root.join("page.tmrs"), "@if[show]@hi@endif@")?;
+
+        let out_file = root.join("templates.rs");
+        let result = codegen::compile_templates(&root, &out_file);
+        assert!(matches!(result, Err(TemplateError::InvalidSyntax(_))));
+
+        fs::remove_dir_all(&root).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_compile_templates_rejects_colliding_sanitized_parameter_names() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("template_rs_codegen_collision_{}", std::process::id()));
+        fs::create_dir_all(&root)?;
+        fs::write(root.join("page.tmrs"), "@[user.name]@ @[user_name]@")?;
+
+        let out_file = root.join("templates.rs");
+        let result = codegen::compile_templates(&root, &out_file);
+        assert!(matches!(result, Err(TemplateError::InvalidSyntax(_))));
+
+        fs::remove_dir_all(&root).ok();
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+/// A value that can be substituted into a template or iterated over.
+///
+/// Dotted placeholder paths (e.g. `@[user.name]@`) are resolved against
+/// nested [`Value::Map`]s, and `@for[item in list]@` iterates over
+/// [`Value::Array`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Array(Vec<Value>),
+    Map(HashMap<String, Value>),
+}
+
+impl Value {
+    /// The string form of this value, if it is a [`Value::String`].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Whether this value is considered "truthy" by `@if[...]@`: a
+    /// non-empty string, array, or map.
+    pub(crate) fn is_truthy(&self) -> bool {
+        match self {
+            Value::String(s) => !s.is_empty(),
+            Value::Array(a) => !a.is_empty(),
+            Value::Map(m) => !m.is_empty(),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}